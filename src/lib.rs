@@ -1,21 +1,321 @@
 #![cfg_attr(not(any(feature = "export-abi", test)), no_main)]
 extern crate alloc;
 
+use alloy_sol_types::sol;
 use stylus_sdk::alloy_primitives::{Address, U256};
 use stylus_sdk::prelude::*;
-use stylus_sdk::{block, console};
+use stylus_sdk::{block, console, evm, msg};
+
+sol! {
+    event CupcakeDispensed(address indexed user, uint256 newBalance, uint256 timestamp);
+    event CooldownActive(address indexed user, uint256 secondsRemaining);
+
+    error Unauthorized(address caller);
+    error InvalidOwner(address owner);
+}
+
+#[derive(SolidityError)]
+pub enum VendingMachineError {
+    Unauthorized(Unauthorized),
+    InvalidOwner(InvalidOwner),
+}
+
+const DEFAULT_COOLDOWN_SECONDS: u64 = 5;
 
 sol_storage! {
     #[entrypoint]
     pub struct VendingMachine {
         mapping(address => uint256) cupcake_balances;
         mapping(address => uint256) cupcake_distribution_times;
+        uint256 cooldown_seconds;
+        bool cooldown_configured;
+        uint256 total_cupcakes;
+        address owner;
+        bool paused;
+    }
+}
+
+// Falls back to the default until set_cooldown has been called, so an
+// explicit cooldown of 0 is distinguishable from uninitialized storage.
+fn effective_cooldown(configured: bool, stored: U256) -> U256 {
+    if configured {
+        stored
+    } else {
+        U256::from(DEFAULT_COOLDOWN_SECONDS)
+    }
+}
+
+fn next_available_time(last_distribution: U256, cooldown: U256) -> U256 {
+    last_distribution.saturating_add(cooldown)
+}
+
+fn cooldown_elapsed(next_available: U256, current_time: U256) -> bool {
+    next_available <= current_time
+}
+
+fn seconds_until(next_available: U256, current_time: U256) -> U256 {
+    next_available.saturating_sub(current_time)
+}
+
+// An unclaimed owner slot is zero, which no caller can match, so admin
+// methods stay locked until set_owner has been called.
+fn check_owner(caller: Address, owner: Address) -> Result<(), VendingMachineError> {
+    if caller != owner {
+        return Err(VendingMachineError::Unauthorized(Unauthorized { caller }));
+    }
+    Ok(())
+}
+
+// Handing ownership to the zero address would reopen the first-caller claim.
+fn validate_new_owner(new_owner: Address) -> Result<(), VendingMachineError> {
+    if new_owner.is_zero() {
+        return Err(VendingMachineError::InvalidOwner(InvalidOwner {
+            owner: new_owner,
+        }));
+    }
+    Ok(())
+}
+
+// Storage values a single dispense decision depends on.
+struct DispenseState {
+    next_available: U256,
+    balance: U256,
+    total: U256,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Dispensed {
+    balance: U256,
+    total: U256,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Rejection {
+    CoolingDown { seconds_remaining: U256 },
+    BalanceFull,
+}
+
+enum DispenseEvent {
+    Dispensed(CupcakeDispensed),
+    CooldownActive(CooldownActive),
+}
+
+// The event logged for a dispense outcome, if any. A full balance is only
+// reported on the debug console.
+fn dispense_event(
+    user: Address,
+    current_time: U256,
+    outcome: &Result<Dispensed, Rejection>,
+) -> Option<DispenseEvent> {
+    match outcome {
+        Ok(dispensed) => Some(DispenseEvent::Dispensed(CupcakeDispensed {
+            user,
+            newBalance: dispensed.balance,
+            timestamp: current_time,
+        })),
+        Err(Rejection::CoolingDown { seconds_remaining }) => {
+            Some(DispenseEvent::CooldownActive(CooldownActive {
+                user,
+                secondsRemaining: *seconds_remaining,
+            }))
+        }
+        Err(Rejection::BalanceFull) => None,
+    }
+}
+
+// Decides whether a user gets a cupcake at `current_time` and, if so, what
+// their balance and the global total become. Rejects rather than overflowing
+// a balance already at U256::MAX.
+fn try_dispense(state: &DispenseState, current_time: U256) -> Result<Dispensed, Rejection> {
+    if !cooldown_elapsed(state.next_available, current_time) {
+        return Err(Rejection::CoolingDown {
+            seconds_remaining: seconds_until(state.next_available, current_time),
+        });
+    }
+    let balance = state
+        .balance
+        .checked_add(U256::from(1))
+        .ok_or(Rejection::BalanceFull)?;
+    Ok(Dispensed {
+        balance,
+        total: state.total.saturating_add(U256::from(1)),
+    })
+}
+
+// Storage that a dispense reads and writes. The contract implements it over
+// its sol_storage fields and the tests over in-memory maps, so both run the
+// same dispense()/dispense_batch() code.
+trait CupcakeStore {
+    fn read_owner(&self) -> Address;
+    fn read_paused(&self) -> bool;
+    fn write_paused(&mut self, value: bool);
+    fn read_cooldown_configured(&self) -> bool;
+    fn read_cooldown_seconds(&self) -> U256;
+    fn write_cooldown(&mut self, cooldown_seconds: U256);
+    fn read_last_distribution(&self, user: Address) -> U256;
+    fn read_balance(&self, user: Address) -> U256;
+    fn read_total(&self) -> U256;
+    fn write_dispensed(&mut self, user: Address, dispensed: &Dispensed, current_time: U256);
+    fn emit(&mut self, event: DispenseEvent);
+
+    fn read_cooldown(&self) -> U256 {
+        effective_cooldown(
+            self.read_cooldown_configured(),
+            self.read_cooldown_seconds(),
+        )
+    }
+
+    fn next_available_time_for(&self, user: Address) -> U256 {
+        next_available_time(self.read_last_distribution(user), self.read_cooldown())
+    }
+}
+
+// Checked once at the top of each dispensing entry point so a paused machine
+// returns before touching any per-user storage.
+fn dispensing_paused<S: CupcakeStore>(store: &S) -> bool {
+    if store.read_paused() {
+        console!("Vending machine is paused; no cupcakes are being dispensed");
+        return true;
+    }
+    false
+}
+
+fn set_paused_by<S: CupcakeStore>(
+    store: &mut S,
+    caller: Address,
+    value: bool,
+) -> Result<(), VendingMachineError> {
+    check_owner(caller, store.read_owner())?;
+    store.write_paused(value);
+    Ok(())
+}
+
+fn set_cooldown_by<S: CupcakeStore>(
+    store: &mut S,
+    caller: Address,
+    cooldown_seconds: U256,
+) -> Result<(), VendingMachineError> {
+    check_owner(caller, store.read_owner())?;
+    store.write_cooldown(cooldown_seconds);
+    Ok(())
+}
+
+// Applies the cooldown check for a single user and, if it has elapsed,
+// increments their balance and records the distribution time.
+fn dispense<S: CupcakeStore>(
+    store: &mut S,
+    user: Address,
+    current_time: U256,
+) -> Result<Dispensed, Rejection> {
+    let state = DispenseState {
+        next_available: store.next_available_time_for(user),
+        balance: store.read_balance(user),
+        total: store.read_total(),
+    };
+    let outcome = try_dispense(&state, current_time);
+
+    if let Ok(dispensed) = &outcome {
+        store.write_dispensed(user, dispensed, current_time);
+    }
+    if let Some(event) = dispense_event(user, current_time, &outcome) {
+        store.emit(event);
+    }
+    match &outcome {
+        Ok(_) => {}
+        Err(Rejection::CoolingDown { .. }) => {
+            // Only printed when built with the `debug` feature.
+            console!(
+                "HTTP 429: Too Many Cupcakes (you must wait at least {} seconds between cupcakes)",
+                store.read_cooldown()
+            );
+        }
+        Err(Rejection::BalanceFull) => {
+            console!("Cupcake balance for {} is already at the maximum", user);
+        }
+    }
+    outcome
+}
+
+// Each user is checked against their own cooldown; a rate-limited entry only
+// yields false for that position.
+fn dispense_batch<S: CupcakeStore>(
+    store: &mut S,
+    users: &[Address],
+    current_time: U256,
+) -> Vec<bool> {
+    if dispensing_paused(store) {
+        return vec![false; users.len()];
+    }
+    users
+        .iter()
+        .map(|&user| dispense(store, user, current_time).is_ok())
+        .collect()
+}
+
+impl CupcakeStore for VendingMachine {
+    fn read_owner(&self) -> Address {
+        self.owner.get()
+    }
+
+    fn read_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    fn write_paused(&mut self, value: bool) {
+        self.paused.set(value);
+    }
+
+    fn read_cooldown_configured(&self) -> bool {
+        self.cooldown_configured.get()
+    }
+
+    fn read_cooldown_seconds(&self) -> U256 {
+        self.cooldown_seconds.get()
+    }
+
+    fn write_cooldown(&mut self, cooldown_seconds: U256) {
+        self.cooldown_seconds.set(cooldown_seconds);
+        self.cooldown_configured.set(true);
+    }
+
+    fn read_last_distribution(&self, user: Address) -> U256 {
+        self.cupcake_distribution_times.get(user)
+    }
+
+    fn read_balance(&self, user: Address) -> U256 {
+        self.cupcake_balances.get(user)
+    }
+
+    fn read_total(&self) -> U256 {
+        self.total_cupcakes.get()
+    }
+
+    fn write_dispensed(&mut self, user: Address, dispensed: &Dispensed, current_time: U256) {
+        self.cupcake_balances.insert(user, dispensed.balance);
+        self.cupcake_distribution_times.insert(user, current_time);
+        self.total_cupcakes.set(dispensed.total);
+    }
+
+    fn emit(&mut self, event: DispenseEvent) {
+        match event {
+            DispenseEvent::Dispensed(event) => evm::log(event),
+            DispenseEvent::CooldownActive(event) => evm::log(event),
+        }
+    }
+}
+
+impl VendingMachine {
+    fn only_owner(&self) -> Result<(), VendingMachineError> {
+        check_owner(msg::sender(), self.owner.get())
     }
 }
 
 #[public]
 impl VendingMachine {
     pub fn give_cupcake_to(&mut self, user_address: Address) -> bool {
+        if dispensing_paused(self) {
+            return false;
+        }
         // Burn gas via repeated storage reads — these are real HostIO boundary
         // crossings that stylus-trace can see and measure.
         // Each .get() call hits storage_cache (warm) or storage_load (cold) HostIO.
@@ -27,27 +327,406 @@ impl VendingMachine {
         if acc == U256::MAX {
             return false;
         }
-        let last_distribution = self.cupcake_distribution_times.get(user_address);
-        let next_available = last_distribution + U256::from(5);
-        let current_time = U256::from(block::timestamp());
+        dispense(self, user_address, U256::from(block::timestamp())).is_ok()
+    }
 
-        if next_available <= current_time {
-            let mut balance_accessor = self.cupcake_balances.setter(user_address);
-            let balance = balance_accessor.get() + U256::from(1);
-            balance_accessor.set(balance);
+    pub fn give_cupcakes_to(&mut self, users: Vec<Address>) -> Vec<bool> {
+        dispense_batch(self, &users, U256::from(block::timestamp()))
+    }
 
-            let mut time_accessor = self.cupcake_distribution_times.setter(user_address);
-            time_accessor.set(current_time);
-            true
-        } else {
-            console!(
-                "HTTP 429: Too Many Cupcakes (you must wait at least 5 seconds between cupcakes)"
-            );
-            false
+    // The first caller claims ownership of a fresh deployment; after that
+    // only the current owner can hand it over. The claim can be front-run, so
+    // deployers should call this straight after deploying.
+    pub fn set_owner(&mut self, new_owner: Address) -> Result<(), VendingMachineError> {
+        validate_new_owner(new_owner)?;
+        if !self.owner.get().is_zero() {
+            self.only_owner()?;
         }
+        self.owner.set(new_owner);
+        Ok(())
+    }
+
+    pub fn get_owner(&self) -> Address {
+        self.owner.get()
+    }
+
+    pub fn set_paused(&mut self, value: bool) -> Result<(), VendingMachineError> {
+        set_paused_by(self, msg::sender(), value)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    pub fn set_cooldown(&mut self, cooldown_seconds: U256) -> Result<(), VendingMachineError> {
+        set_cooldown_by(self, msg::sender(), cooldown_seconds)
+    }
+
+    pub fn get_cooldown(&self) -> U256 {
+        self.read_cooldown()
     }
 
     pub fn get_cupcake_balance_for(&self, user_address: Address) -> U256 {
         self.cupcake_balances.get(user_address)
     }
+
+    pub fn get_last_distribution_time_for(&self, user_address: Address) -> U256 {
+        self.cupcake_distribution_times.get(user_address)
+    }
+
+    pub fn seconds_until_next_cupcake_for(&self, user_address: Address) -> U256 {
+        let next_available = self.next_available_time_for(user_address);
+        seconds_until(next_available, U256::from(block::timestamp()))
+    }
+
+    pub fn get_total_cupcakes(&self) -> U256 {
+        self.total_cupcakes.get()
+    }
+
+    pub fn get_balances(&self, users: Vec<Address>) -> Vec<U256> {
+        // One read per distinct slot, in contrast to the same-slot loop above.
+        users
+            .into_iter()
+            .map(|user| self.cupcake_balances.get(user))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::SolEvent;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+
+    // In-memory CupcakeStore so the shared dispense code can be exercised
+    // without a Stylus VM.
+    #[derive(Default)]
+    struct TestMachine {
+        owner: Address,
+        paused: bool,
+        cooldown_configured: bool,
+        cooldown_seconds: U256,
+        balances: HashMap<Address, U256>,
+        distribution_times: HashMap<Address, U256>,
+        total: U256,
+        events: Vec<DispenseEvent>,
+        // Counts per-user storage reads, to check that pause checks are cheap.
+        user_reads: Cell<u32>,
+    }
+
+    impl CupcakeStore for TestMachine {
+        fn read_owner(&self) -> Address {
+            self.owner
+        }
+
+        fn read_paused(&self) -> bool {
+            self.paused
+        }
+
+        fn write_paused(&mut self, value: bool) {
+            self.paused = value;
+        }
+
+        fn read_cooldown_configured(&self) -> bool {
+            self.cooldown_configured
+        }
+
+        fn read_cooldown_seconds(&self) -> U256 {
+            self.cooldown_seconds
+        }
+
+        fn write_cooldown(&mut self, cooldown_seconds: U256) {
+            self.cooldown_seconds = cooldown_seconds;
+            self.cooldown_configured = true;
+        }
+
+        fn read_last_distribution(&self, user: Address) -> U256 {
+            self.user_reads.set(self.user_reads.get() + 1);
+            self.distribution_times
+                .get(&user)
+                .copied()
+                .unwrap_or_default()
+        }
+
+        fn read_balance(&self, user: Address) -> U256 {
+            self.user_reads.set(self.user_reads.get() + 1);
+            self.balances.get(&user).copied().unwrap_or_default()
+        }
+
+        fn read_total(&self) -> U256 {
+            self.total
+        }
+
+        fn write_dispensed(&mut self, user: Address, dispensed: &Dispensed, current_time: U256) {
+            self.balances.insert(user, dispensed.balance);
+            self.distribution_times.insert(user, current_time);
+            self.total = dispensed.total;
+        }
+
+        fn emit(&mut self, event: DispenseEvent) {
+            self.events.push(event);
+        }
+    }
+
+    impl TestMachine {
+        fn dispense(&mut self, user: Address, now: u64) -> Result<Dispensed, Rejection> {
+            dispense(self, user, U256::from(now))
+        }
+
+        fn dispense_batch(&mut self, users: &[Address], now: u64) -> Vec<bool> {
+            dispense_batch(self, users, U256::from(now))
+        }
+
+        fn set_paused(&mut self, caller: Address, value: bool) -> Result<(), VendingMachineError> {
+            set_paused_by(self, caller, value)
+        }
+
+        fn set_cooldown(
+            &mut self,
+            caller: Address,
+            cooldown_seconds: u64,
+        ) -> Result<(), VendingMachineError> {
+            set_cooldown_by(self, caller, U256::from(cooldown_seconds))
+        }
+    }
+
+    #[test]
+    fn unconfigured_cooldown_defaults_to_five_seconds() {
+        let cooldown = effective_cooldown(false, U256::ZERO);
+        assert_eq!(cooldown, U256::from(5));
+
+        let next_available = next_available_time(U256::from(100), cooldown);
+        assert!(!cooldown_elapsed(next_available, U256::from(104)));
+        assert!(cooldown_elapsed(next_available, U256::from(105)));
+    }
+
+    #[test]
+    fn zero_cooldown_dispenses_every_block() {
+        let owner = Address::repeat_byte(0x0a);
+        let user = Address::repeat_byte(0x0c);
+        let mut machine = TestMachine {
+            owner,
+            ..Default::default()
+        };
+
+        assert!(machine.set_cooldown(owner, 0).is_ok());
+        assert_eq!(machine.read_cooldown(), U256::ZERO);
+
+        for block_time in [100, 100, 101, 102] {
+            assert!(machine.dispense(user, block_time).is_ok());
+        }
+        assert_eq!(machine.balances[&user], U256::from(4));
+    }
+
+    #[test]
+    fn configured_cooldown_replaces_default() {
+        let owner = Address::repeat_byte(0x0a);
+        let user = Address::repeat_byte(0x0c);
+        let mut machine = TestMachine {
+            owner,
+            ..Default::default()
+        };
+
+        assert!(machine.set_cooldown(Address::repeat_byte(0x0b), 0).is_err());
+        assert!(!machine.cooldown_configured);
+
+        assert!(machine.set_cooldown(owner, 60).is_ok());
+        assert!(machine.dispense(user, 100).is_ok());
+        assert_eq!(
+            machine.dispense(user, 159),
+            Err(Rejection::CoolingDown {
+                seconds_remaining: U256::from(1),
+            })
+        );
+        assert!(machine.dispense(user, 160).is_ok());
+    }
+
+    #[test]
+    fn next_available_time_saturates() {
+        let next_available = next_available_time(U256::MAX, U256::from(5));
+        assert_eq!(next_available, U256::MAX);
+    }
+
+    #[test]
+    fn seconds_until_is_zero_once_cooldown_elapsed() {
+        let next_available = next_available_time(U256::from(100), U256::from(5));
+        assert_eq!(seconds_until(next_available, U256::from(105)), U256::ZERO);
+        assert_eq!(seconds_until(next_available, U256::from(500)), U256::ZERO);
+    }
+
+    #[test]
+    fn seconds_until_reports_remaining_wait() {
+        let next_available = next_available_time(U256::from(100), U256::from(5));
+        assert_eq!(
+            seconds_until(next_available, U256::from(102)),
+            U256::from(3)
+        );
+    }
+
+    #[test]
+    fn dispense_increments_balance_and_total() {
+        let state = DispenseState {
+            next_available: U256::from(100),
+            balance: U256::from(2),
+            total: U256::from(7),
+        };
+        assert_eq!(
+            try_dispense(&state, U256::from(100)),
+            Ok(Dispensed {
+                balance: U256::from(3),
+                total: U256::from(8),
+            })
+        );
+    }
+
+    #[test]
+    fn dispense_at_max_balance_is_rejected() {
+        let state = DispenseState {
+            next_available: U256::ZERO,
+            balance: U256::MAX,
+            total: U256::MAX,
+        };
+        assert_eq!(
+            try_dispense(&state, U256::from(100)),
+            Err(Rejection::BalanceFull)
+        );
+    }
+
+    #[test]
+    fn batch_evaluates_each_user_cooldown_independently() {
+        let alice = Address::repeat_byte(0xa1);
+        let bob = Address::repeat_byte(0xb0);
+        let carol = Address::repeat_byte(0xc4);
+
+        let mut machine = TestMachine::default();
+        assert!(machine.dispense(bob, 100).is_ok());
+
+        let results = machine.dispense_batch(&[alice, bob, carol], 102);
+        assert_eq!(results, vec![true, false, true]);
+        assert_eq!(machine.balances[&bob], U256::from(1));
+    }
+
+    #[test]
+    fn total_counts_dispenses_across_users() {
+        let mut machine = TestMachine::default();
+        machine
+            .dispense(Address::repeat_byte(0x01), 100)
+            .expect("first user is served");
+        machine
+            .dispense(Address::repeat_byte(0x02), 100)
+            .expect("second user is served");
+        assert_eq!(machine.total, U256::from(2));
+    }
+
+    #[test]
+    fn second_call_within_cooldown_emits_one_dispensed_event() {
+        let user = Address::repeat_byte(0x0c);
+        let mut machine = TestMachine::default();
+
+        assert!(machine.dispense(user, 100).is_ok());
+        assert!(machine.dispense(user, 100).is_err());
+
+        let dispensed: Vec<&CupcakeDispensed> = machine
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                DispenseEvent::Dispensed(event) => Some(event),
+                DispenseEvent::CooldownActive(_) => None,
+            })
+            .collect();
+        assert_eq!(dispensed.len(), 1);
+        assert_eq!(dispensed[0].user, user);
+        assert_eq!(dispensed[0].newBalance, U256::from(1));
+        assert_eq!(dispensed[0].timestamp, U256::from(100));
+
+        match machine.events.last() {
+            Some(DispenseEvent::CooldownActive(event)) => {
+                assert_eq!(event.user, user);
+                assert_eq!(event.secondsRemaining, U256::from(5));
+            }
+            _ => panic!("expected CooldownActive for the rate-limited call"),
+        }
+    }
+
+    #[test]
+    fn full_balance_emits_no_event() {
+        let user = Address::repeat_byte(0x0c);
+        let mut machine = TestMachine::default();
+        machine.balances.insert(user, U256::MAX);
+
+        assert_eq!(machine.dispense(user, 100), Err(Rejection::BalanceFull));
+        assert!(machine.events.is_empty());
+        assert_eq!(machine.balances[&user], U256::MAX);
+    }
+
+    #[test]
+    fn event_signatures_match_documented_interface() {
+        assert_eq!(
+            CupcakeDispensed::SIGNATURE,
+            "CupcakeDispensed(address,uint256,uint256)"
+        );
+        assert_eq!(CooldownActive::SIGNATURE, "CooldownActive(address,uint256)");
+    }
+
+    #[test]
+    fn only_owner_passes_owner_check() {
+        let owner = Address::repeat_byte(0x0a);
+        let stranger = Address::repeat_byte(0x0b);
+
+        assert!(check_owner(owner, owner).is_ok());
+        assert!(matches!(
+            check_owner(stranger, owner),
+            Err(VendingMachineError::Unauthorized(_))
+        ));
+        // Nobody passes before ownership has been claimed.
+        assert!(check_owner(stranger, Address::ZERO).is_err());
+    }
+
+    #[test]
+    fn zero_address_cannot_become_owner() {
+        assert!(matches!(
+            validate_new_owner(Address::ZERO),
+            Err(VendingMachineError::InvalidOwner(_))
+        ));
+        assert!(validate_new_owner(Address::repeat_byte(0x0a)).is_ok());
+    }
+
+    #[test]
+    fn non_owner_cannot_pause() {
+        let owner = Address::repeat_byte(0x0a);
+        let mut machine = TestMachine {
+            owner,
+            ..Default::default()
+        };
+
+        assert!(machine
+            .set_paused(Address::repeat_byte(0x0b), true)
+            .is_err());
+        assert!(!machine.paused);
+    }
+
+    #[test]
+    fn dispensing_fails_while_paused_and_resumes_after_unpause() {
+        let owner = Address::repeat_byte(0x0a);
+        let user = Address::repeat_byte(0x0c);
+        let other = Address::repeat_byte(0x0d);
+        let mut machine = TestMachine {
+            owner,
+            ..Default::default()
+        };
+
+        assert!(machine.set_paused(owner, true).is_ok());
+        assert_eq!(
+            machine.dispense_batch(&[user, other], 100),
+            vec![false, false]
+        );
+        assert!(machine.balances.is_empty());
+        assert!(machine.distribution_times.is_empty());
+        assert_eq!(machine.user_reads.get(), 0);
+
+        assert!(machine.set_paused(owner, false).is_ok());
+        assert_eq!(machine.dispense_batch(&[user], 100), vec![true]);
+        assert_eq!(machine.balances[&user], U256::from(1));
+    }
 }